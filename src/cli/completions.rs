@@ -0,0 +1,68 @@
+use std::io;
+
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::{arg, ArgMatches, Command};
+use clap_complete::{generate, Shell};
+
+use crate::tasks::{resolve_path, Rukefile};
+use colorized::{Color, Colors};
+
+pub fn completions_command() -> Command {
+    Command::new("completions")
+        .about("Generate a shell completion script")
+        .arg(
+            arg!(<shell> "Shell to generate completions for").value_parser([
+                "bash",
+                "zsh",
+                "fish",
+                "powershell",
+            ]),
+        )
+        .arg(arg!(-f --file <FILE> "Set a Ruke.toml or Rukefile to use"))
+}
+
+/// Prints a completion script for `matches`' shell to stdout, with the
+/// `run` subcommand's recipe names filled in from the resolved Rukefile so
+/// `ruke run <TAB>` offers the user's actual tasks.
+pub fn completions_handler(matches: &ArgMatches, mut root: Command) {
+    let shell = match matches.get_one::<String>("shell").map(String::as_str) {
+        Some("bash") => Shell::Bash,
+        Some("zsh") => Shell::Zsh,
+        Some("fish") => Shell::Fish,
+        Some("powershell") => Shell::PowerShell,
+        _ => {
+            eprintln!("{}", "unsupported shell".color(Colors::RedFg));
+            return;
+        }
+    };
+
+    let filepath = matches.get_one::<String>("file");
+    let recipe_names = resolve_path(filepath)
+        .and_then(|path| Rukefile::new(path).ok())
+        .map(|rukefile| {
+            rukefile
+                .tasks
+                .iter()
+                .map(|task| task.name.clone())
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+
+    if !recipe_names.is_empty() {
+        // Requires clap's `string` feature, needed to build a `PossibleValue`
+        // from an owned, non-'static `String`.
+        let possible_values = recipe_names
+            .iter()
+            .map(|name| PossibleValue::new(name.clone()))
+            .collect::<Vec<PossibleValue>>();
+
+        root = root.mut_subcommand("run", |run| {
+            run.mut_arg("recipe", |arg| {
+                arg.value_parser(PossibleValuesParser::new(possible_values))
+            })
+        });
+    }
+
+    let bin_name = root.get_name().to_string();
+    generate(shell, &mut root, bin_name, &mut io::stdout());
+}
@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{arg, ArgMatches, Command};
+
+use crate::tasks::{resolve_path, Recipe, Rukefile};
+use colorized::{Color, Colors};
+
+const HINTS: &str = "\n# Add more tasks like this:\n# [[tasks]]\n# name = \"test\"\n# command = \"cargo test\"\n# arguments = [\"--all\"]\n";
+
+pub fn init_command() -> Command {
+    Command::new("init")
+        .about("Scaffold a new Ruke.toml in the current directory")
+        .arg(arg!(-f --file <FILE> "Name for the new Ruke.toml or Rukefile"))
+}
+
+pub fn init_handler(matches: &ArgMatches) {
+    let filepath = matches.get_one::<String>("file");
+
+    if let Some(existing) = resolve_path(filepath) {
+        eprintln!(
+            "{}",
+            format!("{} already exists", existing.display()).color(Colors::RedFg)
+        );
+        return;
+    }
+
+    let target = filepath
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("Ruke.toml"));
+
+    let rukefile = Rukefile {
+        tasks: vec![Recipe {
+            name: String::from("build"),
+            command: Some(String::from("echo 'build the project here'")),
+            arguments: None,
+            dependencies: None,
+            script: None,
+        }],
+        variables: None,
+    };
+
+    if let Err(e) = rukefile.update_rukefile(target.clone()) {
+        eprintln!("{:?}", e);
+        return;
+    }
+
+    if let Ok(body) = fs::read_to_string(&target) {
+        let _ = fs::write(&target, format!("{}{}", body, HINTS));
+    }
+
+    println!(
+        "{}",
+        format!("Created {}!", target.display()).color(Colors::GreenFg)
+    );
+}
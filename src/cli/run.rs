@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use clap::{arg, ArgAction, ArgMatches, Command};
+
+use crate::tasks::{default_job_count, resolve_path, Rukefile};
+use colorized::{Color, Colors};
+
+pub fn run_command() -> Command {
+    Command::new("run")
+        .about("Run a recipe, resolving its dependencies first")
+        .arg(arg!([recipe] "Name of the recipe to run"))
+        .arg(arg!(-f --file <FILE> "Set a Ruke.toml or Rukefile to use"))
+        .arg(arg!(-q --quiet "Suppress a successful recipe's output").action(ArgAction::SetTrue))
+        .arg(
+            arg!(-e --env <"KEY=VALUE"> "Override a {{variable}} for this run")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            arg!(-j --jobs ["N"] "Run independent recipes concurrently (defaults to the number of logical CPUs)")
+                .num_args(0..=1),
+        )
+        .alias("r")
+}
+
+pub fn run_handler(matches: &ArgMatches) {
+    let filepath = matches.get_one::<String>("file");
+
+    let filepath = match resolve_path(filepath) {
+        Some(resolved_path) => resolved_path,
+        None => {
+            eprintln!("{}", "rukefile not found".color(Colors::RedFg));
+            return;
+        }
+    };
+
+    let rukefile = match Rukefile::new(filepath) {
+        Ok(rukefile) => rukefile,
+        Err(e) => {
+            eprintln!("{:?}", e);
+            return;
+        }
+    };
+
+    let name = match matches.get_one::<String>("recipe") {
+        Some(name) => name.to_string(),
+        None => {
+            eprintln!("{}", "recipe name is required".color(Colors::RedFg));
+            return;
+        }
+    };
+
+    let quiet = matches.get_flag("quiet");
+
+    let overrides: HashMap<String, String> = matches
+        .get_many::<String>("env")
+        .unwrap_or_default()
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+    if matches.contains_id("jobs") {
+        let jobs = matches
+            .get_one::<String>("jobs")
+            .and_then(|value| value.parse::<usize>().ok())
+            .unwrap_or_else(default_job_count);
+
+        rukefile.run_recipe_parallel(name, quiet, jobs, &overrides);
+    } else {
+        rukefile.run_recipe(name, quiet, &overrides);
+    }
+}
@@ -1,7 +1,11 @@
 pub mod path;
 
 use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 use core::fmt;
 use std::{fs, io, path::PathBuf, process::Command};
@@ -13,8 +17,13 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Recipe {
     pub name: String,
-    pub command: String,
+    pub command: Option<String>,
     pub arguments: Option<Vec<String>>,
+    pub dependencies: Option<Vec<String>>,
+    /// A multiline script body, run instead of `command`. If its first line
+    /// is a shebang (e.g. `#!/usr/bin/env bash`), it's run through the named
+    /// interpreter; otherwise it's piped into `DEFAULT_SHELL`'s stdin.
+    pub script: Option<String>,
 }
 
 impl fmt::Display for Recipe {
@@ -24,11 +33,16 @@ impl fmt::Display for Recipe {
             None => String::from("not defined").color(Colors::YellowFg),
         };
 
+        let command = match &self.command {
+            Some(command) => command.clone(),
+            None => String::from("script-based").color(Colors::YellowFg),
+        };
+
         write!(
             f,
             "> {}\ncommand: {}\narguments: {}\n",
             self.name.color(Colors::GreenFg),
-            self.command.color(Colors::GreenFg),
+            command.color(Colors::GreenFg),
             arguments.color(Colors::GreenFg)
         )
     }
@@ -110,6 +124,7 @@ impl BinaryTree {
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Rukefile {
     pub tasks: Vec<Recipe>,
+    pub variables: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug)]
@@ -118,6 +133,126 @@ pub enum RukefileError {
     TomlError(toml::de::Error),
 }
 
+/// Visitation state for the depth-first topological sort in
+/// `Rukefile::resolve_execution_order`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    White,
+    Gray,
+    Black,
+}
+
+/// Levenshtein edit distance between `a` and `b`, using two rolling DP rows
+/// instead of a full matrix. Used to power "did you mean?" suggestions when
+/// a recipe name can't be found.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b_len = b.chars().count();
+    let mut previous_row: Vec<usize> = (0..=b_len).collect();
+    let mut current_row: Vec<usize> = vec![0; b_len + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current_row[0] = i + 1;
+
+        for (j, cb) in b.chars().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current_row[j + 1] = (previous_row[j + 1] + 1)
+                .min(current_row[j] + 1)
+                .min(previous_row[j] + cost);
+        }
+
+        previous_row.copy_from_slice(&current_row);
+    }
+
+    previous_row[b_len]
+}
+
+/// Maximum edit distance a recipe name can be from `name` and still be
+/// offered as a "did you mean?" suggestion.
+const SUGGESTION_MAX_DISTANCE: usize = 3;
+
+/// Finds the closest recipe name to `name` among `tasks`, within
+/// `SUGGESTION_MAX_DISTANCE`.
+pub fn suggest_recipe(tasks: &[Recipe], name: &str) -> Option<String> {
+    let mut candidates: Vec<(usize, &str)> = tasks
+        .iter()
+        .map(|recipe| (edit_distance(name, &recipe.name), recipe.name.as_str()))
+        .filter(|(distance, _)| *distance <= SUGGESTION_MAX_DISTANCE)
+        .collect();
+
+    candidates.sort_by_key(|(distance, _)| *distance);
+
+    candidates.first().map(|(_, name)| name.to_string())
+}
+
+/// Resolves a `{{key}}` placeholder against, in order of precedence: CLI
+/// `-e key=value` overrides, the `[variables]` table in the Rukefile, then
+/// the process environment.
+fn resolve_variable(
+    key: &str,
+    file_variables: &HashMap<String, String>,
+    overrides: &HashMap<String, String>,
+) -> Option<String> {
+    overrides
+        .get(key)
+        .or_else(|| file_variables.get(key))
+        .cloned()
+        .or_else(|| std::env::var(key).ok())
+}
+
+/// Expands every `{{key}}` placeholder in `template`, erroring out with the
+/// missing key instead of running a broken command.
+fn expand_template(
+    template: &str,
+    file_variables: &HashMap<String, String>,
+    overrides: &HashMap<String, String>,
+) -> Result<String, String> {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+
+        let after_start = &rest[start + 2..];
+        let end = after_start
+            .find("}}")
+            .ok_or_else(|| format!("unterminated `{{{{` placeholder in `{}`", template))?;
+
+        let key = after_start[..end].trim();
+        let value = resolve_variable(key, file_variables, overrides)
+            .ok_or_else(|| format!("missing value for variable `{{{{{}}}}}`", key))?;
+
+        result.push_str(&value);
+        rest = &after_start[end + 2..];
+    }
+
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+/// Number of workers `run_recipe_parallel` uses when `--jobs` is passed
+/// without an explicit count: the number of logical CPUs, or `1` if that
+/// can't be determined.
+pub fn default_job_count() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Shell used to run a `script` body that doesn't start with a shebang; the
+/// body is piped into its stdin.
+const DEFAULT_SHELL: &str = "sh";
+
+fn not_found_message(tasks: &[Recipe], name: &str) -> String {
+    match suggest_recipe(tasks, name) {
+        Some(suggestion) => format!(
+            "recipe `{}` not found, did you mean `{}`?",
+            name, suggestion
+        ),
+        None => format!("recipe `{}` not found", name),
+    }
+}
+
 impl Rukefile {
     pub fn new(path: PathBuf) -> Result<Self, RukefileError> {
         let raw_rukefile = fs::read_to_string(path);
@@ -146,26 +281,305 @@ impl Rukefile {
         Ok(())
     }
 
-    fn find_recipe(&self, name: String) -> Option<Recipe> {
-        let recipe = self.tasks.iter().find(|recipe| recipe.name.eq(&name));
+    /// Resolves `name` and every transitive dependency into a single
+    /// front-to-back execution order using a depth-first topological sort
+    /// (white/gray/black marks). Each recipe appears at most once, even if
+    /// several others depend on it.
+    fn resolve_execution_order(&self, name: &str) -> Result<Vec<Recipe>, String> {
+        let tree = BinaryTree::new(&self.tasks);
+        let mut marks: HashMap<String, Mark> = self
+            .tasks
+            .iter()
+            .map(|recipe| (recipe.name.clone(), Mark::White))
+            .collect();
+        let mut order: Vec<Recipe> = Vec::new();
+
+        Rukefile::visit(&tree, &self.tasks, name, &mut marks, &mut order)?;
+
+        Ok(order)
+    }
+
+    fn visit(
+        tree: &BinaryTree,
+        tasks: &[Recipe],
+        name: &str,
+        marks: &mut HashMap<String, Mark>,
+        order: &mut Vec<Recipe>,
+    ) -> Result<(), String> {
+        match marks.get(name).copied().unwrap_or(Mark::White) {
+            Mark::Black => return Ok(()),
+            Mark::Gray => return Err(format!("dependency cycle detected at recipe `{}`", name)),
+            Mark::White => {}
+        }
+
+        let recipe = tree
+            .search(&name.to_string())
+            .ok_or_else(|| not_found_message(tasks, name))?;
+
+        marks.insert(name.to_string(), Mark::Gray);
+
+        if let Some(dependencies) = &recipe.dependencies {
+            for dependency in dependencies {
+                Rukefile::visit(tree, tasks, dependency, marks, order)?;
+            }
+        }
+
+        marks.insert(name.to_string(), Mark::Black);
+        order.push(recipe);
+
+        Ok(())
+    }
+
+    pub fn run_recipe(&self, name: String, quiet: bool, overrides: &HashMap<String, String>) {
+        let order = match self.resolve_execution_order(&name) {
+            Ok(order) => order,
+            Err(e) => {
+                eprintln!("{}", e.color(Colors::RedFg));
+                return;
+            }
+        };
 
-        recipe.cloned()
+        for recipe in order {
+            let recipe = match self.expand_recipe(&recipe, overrides) {
+                Ok(recipe) => recipe,
+                Err(e) => {
+                    eprintln!("{}", e.color(Colors::RedFg));
+                    return;
+                }
+            };
+
+            if !self.execute_recipe(&recipe, quiet) {
+                eprintln!("{}", "recipe run failed".color(Colors::RedFg));
+                return;
+            }
+        }
     }
 
-    pub fn run_recipe(&self, name: String, quiet: bool) {
-        let recipe = match self.find_recipe(name) {
-            Some(recipe) => recipe,
-            None => {
-                eprintln!("{}", "recipe not found".color(Colors::RedFg));
+    /// Like `run_recipe`, but runs recipes whose dependencies are already
+    /// satisfied concurrently, up to `jobs` at a time. Workers pull ready
+    /// recipes off a shared queue and, on completion, decrement the
+    /// in-degree of their dependents, pushing any that reach zero.
+    pub fn run_recipe_parallel(
+        &self,
+        name: String,
+        quiet: bool,
+        jobs: usize,
+        overrides: &HashMap<String, String>,
+    ) {
+        let recipes = match self.resolve_execution_order(&name) {
+            Ok(recipes) => recipes,
+            Err(e) => {
+                eprintln!("{}", e.color(Colors::RedFg));
                 return;
             }
         };
 
-        let command = recipe.command.split(' ').collect::<Vec<&str>>();
+        let mut in_degree: HashMap<String, usize> = HashMap::new();
+        let mut dependents: HashMap<String, Vec<String>> = HashMap::new();
+
+        for recipe in &recipes {
+            let degree = recipe
+                .dependencies
+                .as_ref()
+                .map(|dependencies| dependencies.len())
+                .unwrap_or(0);
+            in_degree.insert(recipe.name.clone(), degree);
+
+            if let Some(dependencies) = &recipe.dependencies {
+                for dependency in dependencies {
+                    dependents
+                        .entry(dependency.clone())
+                        .or_default()
+                        .push(recipe.name.clone());
+                }
+            }
+        }
+
+        let ready: VecDeque<Recipe> = recipes
+            .iter()
+            .filter(|recipe| in_degree[&recipe.name] == 0)
+            .cloned()
+            .collect();
+
+        let total = recipes.len();
+        let recipes_by_name: HashMap<String, Recipe> = recipes
+            .into_iter()
+            .map(|recipe| (recipe.name.clone(), recipe))
+            .collect();
+
+        let queue = Arc::new(Mutex::new(ready));
+        let in_degree = Arc::new(Mutex::new(in_degree));
+        let dependents = Arc::new(dependents);
+        let recipes_by_name = Arc::new(recipes_by_name);
+        let remaining = Arc::new(Mutex::new(total));
+        let failed = Arc::new(Mutex::new(false));
+        let print_lock = Arc::new(Mutex::new(()));
+        let overrides = Arc::new(overrides.clone());
+        let rukefile = Arc::new(self.clone());
+
+        let workers = (0..jobs.max(1))
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                let in_degree = Arc::clone(&in_degree);
+                let dependents = Arc::clone(&dependents);
+                let recipes_by_name = Arc::clone(&recipes_by_name);
+                let remaining = Arc::clone(&remaining);
+                let failed = Arc::clone(&failed);
+                let print_lock = Arc::clone(&print_lock);
+                let overrides = Arc::clone(&overrides);
+                let rukefile = Arc::clone(&rukefile);
+
+                thread::spawn(move || loop {
+                    if *failed.lock().unwrap() || *remaining.lock().unwrap() == 0 {
+                        return;
+                    }
+
+                    let recipe = queue.lock().unwrap().pop_front();
+
+                    let recipe = match recipe {
+                        Some(recipe) => recipe,
+                        None => {
+                            thread::sleep(Duration::from_millis(10));
+                            continue;
+                        }
+                    };
+
+                    let expanded = match rukefile.expand_recipe(&recipe, &overrides) {
+                        Ok(expanded) => expanded,
+                        Err(e) => {
+                            let _guard = print_lock.lock().unwrap();
+                            eprintln!("{}", e.color(Colors::RedFg));
+                            *failed.lock().unwrap() = true;
+                            return;
+                        }
+                    };
+
+                    let output = match rukefile.run_recipe_to_output(&expanded) {
+                        Ok(output) => output,
+                        Err(e) => {
+                            let _guard = print_lock.lock().unwrap();
+                            eprintln!("{}", e.color(Colors::RedFg));
+                            *failed.lock().unwrap() = true;
+                            return;
+                        }
+                    };
+
+                    let success = {
+                        let _guard = print_lock.lock().unwrap();
+                        rukefile.report_output(&output, quiet)
+                    };
+
+                    if !success {
+                        *failed.lock().unwrap() = true;
+                        return;
+                    }
+
+                    *remaining.lock().unwrap() -= 1;
+
+                    if let Some(dependent_names) = dependents.get(&recipe.name) {
+                        let mut degrees = in_degree.lock().unwrap();
+                        let mut queue = queue.lock().unwrap();
+
+                        for dependent in dependent_names {
+                            if let Some(degree) = degrees.get_mut(dependent) {
+                                *degree -= 1;
+                                if *degree == 0 {
+                                    if let Some(next) = recipes_by_name.get(dependent) {
+                                        queue.push_back(next.clone());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            let _ = worker.join();
+        }
+
+        if *failed.lock().unwrap() {
+            eprintln!("{}", "recipe run failed".color(Colors::RedFg));
+        }
+    }
+
+    /// Expands `{{key}}` placeholders in `recipe.command`, `recipe.script`
+    /// and `recipe.arguments` against the file's `[variables]` table, the
+    /// environment, and `overrides` (`-e key=value` on the CLI).
+    fn expand_recipe(
+        &self,
+        recipe: &Recipe,
+        overrides: &HashMap<String, String>,
+    ) -> Result<Recipe, String> {
+        let no_variables = HashMap::new();
+        let file_variables = self.variables.as_ref().unwrap_or(&no_variables);
+
+        let command = match &recipe.command {
+            Some(command) => Some(expand_template(command, file_variables, overrides)?),
+            None => None,
+        };
+
+        let script = match &recipe.script {
+            Some(script) => Some(expand_template(script, file_variables, overrides)?),
+            None => None,
+        };
+
+        let arguments = match &recipe.arguments {
+            Some(arguments) => Some(
+                arguments
+                    .iter()
+                    .map(|argument| expand_template(argument, file_variables, overrides))
+                    .collect::<Result<Vec<String>, String>>()?,
+            ),
+            None => None,
+        };
+
+        Ok(Recipe {
+            name: recipe.name.clone(),
+            command,
+            arguments,
+            dependencies: recipe.dependencies.clone(),
+            script,
+        })
+    }
+
+    /// Runs `recipe` and prints its output, returning whether it exited
+    /// successfully.
+    fn execute_recipe(&self, recipe: &Recipe, quiet: bool) -> bool {
+        match self.run_recipe_to_output(recipe) {
+            Ok(output) => self.report_output(&output, quiet),
+            Err(e) => {
+                eprintln!("{}", e.color(Colors::RedFg));
+                false
+            }
+        }
+    }
+
+    /// Runs `recipe` to completion and returns its output, without printing
+    /// anything. Kept separate from `execute_recipe` so callers (like the
+    /// parallel scheduler) can run independent recipes concurrently and only
+    /// serialize the printing of their results.
+    fn run_recipe_to_output(&self, recipe: &Recipe) -> Result<std::process::Output, String> {
+        match &recipe.script {
+            Some(script) => self.run_script(recipe, script),
+            None => self.run_command(recipe),
+        }
+    }
+
+    fn run_command(&self, recipe: &Recipe) -> Result<std::process::Output, String> {
+        let command_line = recipe.command.as_ref().ok_or_else(|| {
+            format!(
+                "recipe `{}` has neither a command nor a script",
+                recipe.name
+            )
+        })?;
+
+        let command = command_line.split(' ').collect::<Vec<&str>>();
 
         let positional_arguments = command[1..].iter().map(|argument| argument.to_string());
 
-        let arguments = match recipe.arguments {
+        let arguments = match recipe.arguments.clone() {
             Some(mut arguments) => {
                 positional_arguments.for_each(|argument| arguments.push(argument));
 
@@ -174,10 +588,86 @@ impl Rukefile {
             None => positional_arguments.collect::<Vec<String>>(),
         };
 
-        let output = Command::new(command[0])
+        Command::new(command[0])
             .args(arguments)
             .output()
-            .expect("failed to execute command");
+            .map_err(|e| format!("failed to run `{}`: {}", recipe.name, e))
+    }
+
+    /// Runs a multiline `script` body: through the interpreter named in its
+    /// shebang if it has one, otherwise through `DEFAULT_SHELL`'s stdin.
+    fn run_script(&self, recipe: &Recipe, script: &str) -> Result<std::process::Output, String> {
+        let first_line = script.lines().next().unwrap_or_default();
+
+        let output = match first_line.strip_prefix("#!") {
+            Some(shebang) => self.run_via_shebang(recipe, shebang.trim(), script),
+            None => self.run_via_default_shell(recipe, script),
+        };
+
+        output.map_err(|e| e.to_string())
+    }
+
+    fn run_via_shebang(
+        &self,
+        recipe: &Recipe,
+        shebang: &str,
+        script: &str,
+    ) -> io::Result<std::process::Output> {
+        let mut interpreter_parts = shebang.split_whitespace();
+        let interpreter = interpreter_parts
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty shebang"))?;
+        let interpreter_args = interpreter_parts.collect::<Vec<&str>>();
+
+        let mut script_path = std::env::temp_dir();
+        script_path.push(format!("ruke-{}-{}.sh", recipe.name, std::process::id()));
+
+        fs::write(&script_path, script)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&script_path, fs::Permissions::from_mode(0o755))?;
+        }
+
+        let result = Command::new(interpreter)
+            .args(interpreter_args)
+            .arg(&script_path)
+            .args(recipe.arguments.clone().unwrap_or_default())
+            .output();
+
+        let _ = fs::remove_file(&script_path);
+
+        result
+    }
+
+    fn run_via_default_shell(
+        &self,
+        recipe: &Recipe,
+        script: &str,
+    ) -> io::Result<std::process::Output> {
+        use std::io::Write;
+        use std::process::Stdio;
+
+        let mut child = Command::new(DEFAULT_SHELL)
+            .arg("-s")
+            .args(recipe.arguments.clone().unwrap_or_default())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let Some(stdin) = child.stdin.as_mut() {
+            stdin.write_all(script.as_bytes())?;
+        }
+
+        child.wait_with_output()
+    }
+
+    fn report_output(&self, output: &std::process::Output, quiet: bool) -> bool {
+        if quiet && output.status.success() {
+            return true;
+        }
 
         let is_success_and_not_quiet = output.status.success() && !quiet;
 
@@ -188,6 +678,8 @@ impl Rukefile {
 
         let stdout = String::from_utf8_lossy(&output.stdout);
         println!("{}", stdout);
+
+        output.status.success()
     }
 
     pub fn list_tasks(&self) {
@@ -205,8 +697,10 @@ impl Rukefile {
     pub fn add_task(&mut self, name: String, command: String) {
         let task = Recipe {
             name,
-            command,
+            command: Some(command),
             arguments: None,
+            dependencies: None,
+            script: None,
         };
         self.tasks.push(task);
     }